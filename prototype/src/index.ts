@@ -27,6 +27,7 @@ type BufferAssignment = {
   port: string //
   buffer: Buffer
   compensation?: number
+  misalignment?: number
 }
 
 type Port = {
@@ -34,9 +35,16 @@ type Port = {
   connection?: {
     node: Node
     port: string
+    // uncompensated edges (cue/monitor sends) skip delay compensation. only
+    // read from the input port's side of the connection.
+    uncompensated?: boolean
+    // fixed delay of the edge itself, e.g. an external hardware insert. only
+    // read from the input port's side of the connection.
+    delay?: number
   }
   latency?: number
   compensation?: number
+  misalignment?: number
   buffer?: Buffer
 }
 
@@ -46,6 +54,9 @@ type Node = {
   outputs: Port[]
   latency?: number
   delay: number
+  // delays[i][j] is the delay from input i to output j, where it differs from
+  // the delay of the node
+  delays?: number[][]
   visited: boolean
   process: (inputs: BufferAssignment[], outputs: BufferAssignment[]) => void
 }
@@ -56,6 +67,66 @@ type Scheduled = {
   outputs: BufferAssignment[]
 }
 
+// one input along the path that determines the latency of a node
+type LatencyStep = {
+  node: Node
+  port: string
+  latency: number
+}
+
+// thrown when compensating the inputs of a node would exceed the latency budget
+class LatencyBudgetError extends Error {
+  node: Node
+  latency: number
+  budget: number
+  path: LatencyStep[]
+  constructor(
+    node: Node,
+    latency: number,
+    budget: number,
+    path: LatencyStep[]
+  ) {
+    let steps = path.map(
+      (step) => `${step.node.name}.${step.port} (${step.latency})`
+    )
+    super(
+      `${node.name} needs a latency of ${latency}, over the budget of ${budget}: ${steps.join(" -> ")}`
+    )
+    this.node = node
+    this.latency = latency
+    this.budget = budget
+    this.path = path
+  }
+}
+
+// trace the compensated inputs that determine the latency of a node, from the
+// source of the path to the node itself
+function latencyPath(node: Node): LatencyStep[] {
+  let path: LatencyStep[] = []
+  let current: Node | undefined = node
+  while (current) {
+    let latest = current.inputs
+      .filter((port) => port.connection && !port.connection.uncompensated)
+      .reduce<Port | undefined>(
+        (prev, curr) =>
+          !prev || (curr.latency as number) > (prev.latency as number)
+            ? curr
+            : prev,
+        undefined
+      )
+    if (!latest) {
+      break
+    }
+    path.unshift({
+      node: current,
+      port: latest.name,
+      latency: latest.latency as number,
+    })
+    current = latest.connection?.node
+  }
+  return path
+}
+
 function delay(buffer: Buffer, amount: number) {
   console.log(`delaying ${buffer} by ${amount}`)
   /* ... */
@@ -72,7 +143,7 @@ function render(schedule: Scheduled[]) {
   }
 }
 
-function schedule(root: Node): Scheduled[] {
+function schedule(root: Node, budget?: number): Scheduled[] {
   let order: Scheduled[] = []
   let bufferStack: Buffer[] = []
   function acquireBuffer(): Buffer {
@@ -85,7 +156,7 @@ function schedule(root: Node): Scheduled[] {
   function releaseBuffer(buffer: Buffer) {
     bufferStack.push(buffer)
   }
-  function visit(node: Node): { latency: number; outputs: BufferAssignment[] } {
+  function visit(node: Node): (BufferAssignment & { latency: number })[] {
     if (!node.visited) {
       // for each output in this port, acquire a new buffer
       for (let output of node.outputs) {
@@ -97,12 +168,17 @@ function schedule(root: Node): Scheduled[] {
         // if the input port is connected, solve the node on the other side
         // and find the corresponding output buffer
         if (input.connection) {
-          let { latency, outputs } = visit(input.connection.node)
-          let correspondingBuffer = outputs.find(
-            (assn) => assn.port === input.name
-          )
-          input.latency = latency
-          input.buffer = correspondingBuffer?.buffer
+          let { node: upstream, port } = input.connection
+          let outputs = visit(upstream)
+          let correspondingBuffer = outputs.find((assn) => assn.port === port)
+          if (!correspondingBuffer) {
+            throw new Error(
+              `${node.name}.${input.name} is connected to ${upstream.name}.${port}, which does not exist`
+            )
+          }
+          input.latency =
+            correspondingBuffer.latency + (input.connection.delay ?? 0)
+          input.buffer = correspondingBuffer.buffer
         } else {
           // if there is no connection, acquire a new buffer and set the latency.
           input.latency = 0
@@ -117,21 +193,62 @@ function schedule(root: Node): Scheduled[] {
         releaseBuffer(output.buffer as Buffer)
       }
 
-      // compute max latency
-      let maxInputLatency = node.inputs
+      // compute max latency, ignoring uncompensated inputs
+      let compensatedInputs = node.inputs.filter(
+        (port) => !port.connection?.uncompensated
+      )
+      let maxInputLatency = compensatedInputs
         .map((port) => port.latency as number)
         .reduce((prev, curr) => Math.max(prev, curr), 0)
 
-      // compute input compensations
+      // compensation aligns every input to the max input latency, so refuse to
+      // schedule it if that is over the budget.
+      if (budget !== undefined && maxInputLatency > budget) {
+        throw new LatencyBudgetError(
+          node,
+          maxInputLatency,
+          budget,
+          latencyPath(node)
+        )
+      }
+
+      // compute input compensations. uncompensated inputs take the low latency
+      // path and are never delayed, so record their misalignment instead: how
+      // far ahead of the compensated inputs they arrive (negative if behind).
+      // without compensated inputs there is nothing to be misaligned against.
       for (let input of node.inputs) {
         let compensation = maxInputLatency - (input.latency as number)
         if (compensation != 0) {
-          input.compensation = compensation
+          if (!input.connection?.uncompensated) {
+            input.compensation = compensation
+          } else if (compensatedInputs.length > 0) {
+            input.misalignment = compensation
+          }
         }
       }
 
-      // update the latency of this node
-      node.latency = maxInputLatency + node.delay
+      // find when each input is processed. compensated inputs are aligned to the
+      // max input latency, uncompensated inputs are processed as they arrive.
+      let alignedLatencies = node.inputs.map((port) =>
+        port.connection?.uncompensated
+          ? (port.latency as number)
+          : maxInputLatency
+      )
+
+      // update the latency of each output. outputs carry every input, so their
+      // latency must cover the latest input through its delay to that output.
+      node.outputs.forEach((output, j) => {
+        let latencies = alignedLatencies.map(
+          (latency, i) => latency + (node.delays?.[i]?.[j] ?? node.delay)
+        )
+        output.latency = latencies.length ? Math.max(...latencies) : node.delay
+      })
+
+      // update the latency of this node, the latest any signal leaves it. nodes
+      // without outputs use the latest input plus their delay.
+      node.latency = node.outputs.length
+        ? Math.max(...node.outputs.map((port) => port.latency as number))
+        : Math.max(maxInputLatency, ...alignedLatencies) + node.delay
 
       // collect input and output buffer assignments
       let inputs = node.inputs.map((port) => {
@@ -139,6 +256,7 @@ function schedule(root: Node): Scheduled[] {
           port: port.name,
           buffer: port.buffer as Buffer,
           compensation: port.compensation,
+          misalignment: port.misalignment,
         }
       })
       let outputs = node.outputs.map((port) => {
@@ -152,18 +270,15 @@ function schedule(root: Node): Scheduled[] {
       node.visited = true
     }
 
-    // finally, return the latency of this node and the output buffer assignments -
-    // relative to their corresponding input port.
-    return {
-      latency: node.latency as number,
-      outputs: node.outputs.map((port) => {
-        return {
-          // this will never be used if the connection doesn't exist
-          port: port.connection ? port.connection.port : "",
-          buffer: port.buffer as Buffer,
-        }
-      }),
-    }
+    // finally, return the output buffer assignments with their latencies, so the
+    // caller can find the output it is connected to.
+    return node.outputs.map((port) => {
+      return {
+        port: port.name,
+        buffer: port.buffer as Buffer,
+        latency: port.latency as number,
+      }
+    })
   }
   visit(root)
   return order
@@ -177,7 +292,7 @@ let source: Node = {
   process: (i, o) => console.log("called Source"),
   delay: 0,
   inputs: [],
-  outputs: [{ name: "out1" }, { name: "out2" }],
+  outputs: [{ name: "out1" }, { name: "out2" }, { name: "out3" }],
   visited: false,
 }
 
@@ -199,22 +314,44 @@ let right: Node = {
   visited: false,
 }
 
+// a cue send that arrives behind the other inputs of the sink
+let cue: Node = {
+  name: "Cue",
+  process: (i, o) => console.log("called Cue"),
+  delay: 4,
+  inputs: [{ name: "in1" }],
+  outputs: [{ name: "out1" }],
+  visited: false,
+}
+
 let sink: Node = {
   name: "Sink",
-  process: (i, o) => console.log("called Sink"),
+  process: (i, o) => {
+    console.log("called Sink")
+    for (let { port, misalignment } of i) {
+      if (misalignment) {
+        console.log(`${port} is misaligned by ${misalignment}`)
+      }
+    }
+  },
   delay: 0,
-  inputs: [{ name: "in1" }, { name: "in2" }],
+  inputs: [{ name: "in1" }, { name: "in2" }, { name: "in3" }],
   outputs: [],
   visited: false,
 }
 
 source.outputs[0].connection = { node: left, port: "in1" }
 source.outputs[1].connection = { node: right, port: "in1" }
+source.outputs[2].connection = { node: cue, port: "in1" }
 left.inputs[0].connection = { node: source, port: "out1" }
 right.inputs[0].connection = { node: source, port: "out2" }
+cue.inputs[0].connection = { node: source, port: "out3" }
 left.outputs[0].connection = { node: sink, port: "in1" }
 right.outputs[0].connection = { node: sink, port: "in2" }
-sink.inputs[0].connection = { node: left, port: "out1" }
-sink.inputs[1].connection = { node: right, port: "out2" }
+cue.outputs[0].connection = { node: sink, port: "in3" }
+// Left is patched into Sink through a hardware insert with a delay of 2
+sink.inputs[0].connection = { node: left, port: "out1", delay: 2 }
+sink.inputs[1].connection = { node: right, port: "out1" }
+sink.inputs[2].connection = { node: cue, port: "out1", uncompensated: true }
 
 render(schedule(sink))